@@ -8,9 +8,10 @@ use s3etag::Context;
 #[command(name = "s3etag")]
 #[command(about = "Calculate S3 ETag for multipart uploads")]
 struct Cli {
-    /// Chunk size in MB
-    #[arg(short, long, default_value_t = 8)]
-    chunk_size: u64,
+    /// Chunk size in MB. If omitted while verifying against --etag, the
+    /// chunk size is recovered from the expected ETag's part count instead.
+    #[arg(short, long)]
+    chunk_size: Option<u64>,
 
     /// File path
     file: PathBuf,
@@ -28,27 +29,46 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         std::process::exit(2);
     }
 
-    let mut file = File::open(cli.file)?;
-    let chunk_size_bytes = cli.chunk_size * 1024 * 1024;
+    match (&cli.etag, cli.chunk_size) {
+        (Some(expected), None) => {
+            let data = std::fs::read(&cli.file)?;
+            let trimmed = expected.trim_matches('"');
 
-    let mut context = Context::with_chunk_size(chunk_size_bytes as usize);
-    std::io::copy(&mut file, &mut context)?;
+            match s3etag::recover_chunk_size(&data, trimmed) {
+                Some(chunk_size) => {
+                    eprintln!("recovered chunk size: {} MiB", chunk_size / (1024 * 1024));
+                    println!("TRUE");
+                }
+                None => {
+                    println!("FALSE");
+                    std::process::exit(1);
+                }
+            }
+        }
+        _ => {
+            let mut file = File::open(&cli.file)?;
+            let chunk_size_bytes = cli.chunk_size.unwrap_or(8) * 1024 * 1024;
 
-    let digest = context.finalize();
-    let hash = format!("{digest:x}");
+            let mut context: Context = Context::with_chunk_size(chunk_size_bytes as usize);
+            std::io::copy(&mut file, &mut context)?;
 
-    match cli.etag {
-        Some(ref expected) => {
-            let trimmed = expected.trim_matches('"');
-            if hash == trimmed {
-                println!("TRUE");
-                return Ok(());
-            } else {
-                println!("FALSE");
-                std::process::exit(1);
+            let digest = context.finalize();
+            let hash = format!("{digest:x}");
+
+            match cli.etag {
+                Some(ref expected) => {
+                    let trimmed = expected.trim_matches('"');
+                    if hash == trimmed {
+                        println!("TRUE");
+                    } else {
+                        println!("FALSE");
+                        std::process::exit(1);
+                    }
+                }
+                None => println!("{digest:x}"),
             }
         }
-        _ => println!("{digest:x}"),
     }
+
     Ok(())
 }
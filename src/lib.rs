@@ -1,4 +1,5 @@
-//! A library for computing chunked MD5 digests.
+//! A library for computing chunked digests, generic over the hashing
+//! algorithm (MD5 by default) via [`digest::Digest`].
 //!
 //! ## Example
 //!
@@ -8,15 +9,19 @@
 //! ```
 //!
 
-#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+use digest::Digest as DigestAlgorithm;
+use md5::Md5;
+use rayon::prelude::*;
+
+#[derive(Clone, Eq, Hash, PartialEq)]
 pub struct Digest {
-    hash: [u8; 16],
+    hash: Vec<u8>,
     parts: usize,
 }
 
 impl Digest {
     #[inline]
-    pub fn hash(&self) -> &[u8; 16] {
+    pub fn hash(&self) -> &[u8] {
         &self.hash
     }
 
@@ -26,7 +31,7 @@ impl Digest {
     }
 }
 
-impl core::convert::From<Digest> for [u8; 16] {
+impl core::convert::From<Digest> for Vec<u8> {
     #[inline]
     fn from(digest: Digest) -> Self {
         digest.hash
@@ -58,7 +63,7 @@ impl core::fmt::Display for Digest {
 }
 
 impl core::ops::Deref for Digest {
-    type Target = [u8; 16];
+    type Target = [u8];
 
     #[inline]
     fn deref(&self) -> &Self::Target {
@@ -73,16 +78,81 @@ impl core::ops::DerefMut for Digest {
     }
 }
 
+const CONTEXT_STATE_VERSION: u8 = 1;
+
+/// Errors that can occur while restoring a [`Context`] from its serialized
+/// form via [`Context::from_bytes`].
+#[derive(Debug)]
+pub enum FromBytesError {
+    /// The serialized state was produced by an unsupported format version.
+    UnsupportedVersion(u8),
+    /// The byte slice ended before a complete [`Context`] could be decoded.
+    Truncated,
+    /// The decoded state violates an invariant `Context` keeps (a non-zero
+    /// `chunk_size`, and a `current_chunk` shorter than `chunk_size`). A zero
+    /// `chunk_size` would hang the next `consume`, and a `current_chunk`
+    /// longer than `chunk_size` would make it panic.
+    InvalidState,
+}
+
+impl core::fmt::Display for FromBytesError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            FromBytesError::UnsupportedVersion(version) => {
+                write!(f, "unsupported Context state version: {version}")
+            }
+            FromBytesError::Truncated => write!(f, "truncated Context state"),
+            FromBytesError::InvalidState => write!(f, "invalid Context state"),
+        }
+    }
+}
+
+impl std::error::Error for FromBytesError {}
+
+fn take_u8(cursor: &mut &[u8]) -> Result<u8, FromBytesError> {
+    let (&byte, rest) = cursor.split_first().ok_or(FromBytesError::Truncated)?;
+    *cursor = rest;
+    Ok(byte)
+}
+
+fn take_u64(cursor: &mut &[u8]) -> Result<u64, FromBytesError> {
+    if cursor.len() < 8 {
+        return Err(FromBytesError::Truncated);
+    }
+    let (head, rest) = cursor.split_at(8);
+    *cursor = rest;
+    Ok(u64::from_le_bytes(head.try_into().unwrap()))
+}
+
+fn take_bytes(cursor: &mut &[u8]) -> Result<Vec<u8>, FromBytesError> {
+    let len = take_u64(cursor)? as usize;
+    if cursor.len() < len {
+        return Err(FromBytesError::Truncated);
+    }
+    let (head, rest) = cursor.split_at(len);
+    *cursor = rest;
+    Ok(head.to_vec())
+}
+
+/// Chunked digest accumulator, generic over the [`digest::Digest`] algorithm
+/// `D` used to hash each chunk and the final combined hash.
+///
+/// Plain `Context` in type position (e.g. `let ctx: Context = Context::new();`)
+/// defaults to [`Md5`], matching the classic S3 multipart ETag algorithm. A
+/// bare `Context::new()` or `Context::with_chunk_size(..)` call does not apply
+/// that default: `D` is inferred, so it needs an annotation when nothing else
+/// fixes it.
 #[derive(Clone)]
-pub struct Context {
+pub struct Context<D: DigestAlgorithm = Md5> {
     combined_hashes: Vec<u8>,
     current_chunk: Vec<u8>,
     chunk_size: usize,
     chunk_count: usize,
     total_bytes: u64,
+    _algorithm: core::marker::PhantomData<D>,
 }
 
-impl Context {
+impl<D: DigestAlgorithm> Context<D> {
     #[inline]
     pub fn new() -> Self {
         Self::with_chunk_size(8 * 1024 * 1024)
@@ -96,18 +166,21 @@ impl Context {
             chunk_size,
             chunk_count: 0,
             total_bytes: 0,
+            _algorithm: core::marker::PhantomData,
         }
     }
 
     #[inline]
     pub fn with_capacity(chunk_size: usize, total_size: u64) -> Self {
         let estimated_chunks = total_size.div_ceil(chunk_size as u64) as usize;
+        let output_size = <D as DigestAlgorithm>::output_size();
         Self {
-            combined_hashes: Vec::with_capacity(estimated_chunks * 16),
+            combined_hashes: Vec::with_capacity(estimated_chunks * output_size),
             current_chunk: Vec::with_capacity(chunk_size),
             chunk_size,
             chunk_count: 0,
             total_bytes: 0,
+            _algorithm: core::marker::PhantomData,
         }
     }
 
@@ -124,8 +197,10 @@ impl Context {
             remaining = &remaining[to_take..];
 
             if self.current_chunk.len() == self.chunk_size {
-                let hash = md5::compute(&self.current_chunk);
-                self.combined_hashes.extend_from_slice(&hash.0);
+                let mut hasher = D::new();
+                hasher.update(&self.current_chunk);
+                let hash = hasher.finalize();
+                self.combined_hashes.extend_from_slice(&hash);
                 self.current_chunk.clear();
                 self.chunk_count += 1;
             }
@@ -134,14 +209,18 @@ impl Context {
 
     pub fn finalize(mut self) -> Digest {
         if !self.current_chunk.is_empty() {
-            let hash = md5::compute(&self.current_chunk);
-            self.combined_hashes.extend_from_slice(&hash.0);
+            let mut hasher = D::new();
+            hasher.update(&self.current_chunk);
+            let hash = hasher.finalize();
+            self.combined_hashes.extend_from_slice(&hash);
             self.chunk_count += 1;
         }
 
-        let final_hash = md5::compute(&self.combined_hashes);
+        let mut hasher = D::new();
+        hasher.update(&self.combined_hashes);
+        let final_hash = hasher.finalize();
         Digest {
-            hash: final_hash.0,
+            hash: final_hash.to_vec(),
             parts: self.chunk_count,
         }
     }
@@ -149,23 +228,129 @@ impl Context {
     pub fn total_bytes(&self) -> u64 {
         self.total_bytes
     }
+
+    /// Number of parts the final [`Digest`]'s `-N` suffix will report,
+    /// counting the in-progress `current_chunk` if it holds any bytes.
+    ///
+    /// The `digest::Update`/`FixedOutput`/`Reset` impls below make `Context`
+    /// usable anywhere a `digest::Digest` is expected, but that trait's
+    /// fixed-size output has no room for the S3 part count, so it is
+    /// exposed here instead.
+    pub fn parts(&self) -> usize {
+        self.chunk_count + usize::from(!self.current_chunk.is_empty())
+    }
+
+    /// Serialize the current hashing state so it can be checkpointed and
+    /// later restored with [`Context::from_bytes`].
+    ///
+    /// The encoding is a small versioned, length-prefixed format: a version
+    /// byte followed by `chunk_size`, `chunk_count` and `total_bytes` as
+    /// little-endian `u64`s, then `combined_hashes` and `current_chunk` each
+    /// prefixed with their length as a little-endian `u64`.
+    pub fn into_bytes(self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(
+            1 + 8 * 3 + 8 + self.combined_hashes.len() + 8 + self.current_chunk.len(),
+        );
+        buf.push(CONTEXT_STATE_VERSION);
+        buf.extend_from_slice(&(self.chunk_size as u64).to_le_bytes());
+        buf.extend_from_slice(&(self.chunk_count as u64).to_le_bytes());
+        buf.extend_from_slice(&self.total_bytes.to_le_bytes());
+        buf.extend_from_slice(&(self.combined_hashes.len() as u64).to_le_bytes());
+        buf.extend_from_slice(&self.combined_hashes);
+        buf.extend_from_slice(&(self.current_chunk.len() as u64).to_le_bytes());
+        buf.extend_from_slice(&self.current_chunk);
+        buf
+    }
+
+    /// Restore a [`Context`] previously serialized with
+    /// [`Context::into_bytes`].
+    ///
+    /// Further calls to [`Context::consume`] and [`Context::finalize`]
+    /// produce the identical [`Digest`] as if the original `Context` had
+    /// never been interrupted, including when `current_chunk` was holding a
+    /// partially filled chunk. The restored `Context` must be parameterized
+    /// with the same algorithm `D` that produced the serialized bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FromBytesError> {
+        let cursor = &mut { bytes };
+
+        let version = take_u8(cursor)?;
+        if version != CONTEXT_STATE_VERSION {
+            return Err(FromBytesError::UnsupportedVersion(version));
+        }
+
+        let chunk_size = take_u64(cursor)? as usize;
+        let chunk_count = take_u64(cursor)? as usize;
+        let total_bytes = take_u64(cursor)?;
+        let combined_hashes = take_bytes(cursor)?;
+        let current_chunk = take_bytes(cursor)?;
+
+        if chunk_size == 0 || current_chunk.len() >= chunk_size {
+            return Err(FromBytesError::InvalidState);
+        }
+
+        Ok(Self {
+            combined_hashes,
+            current_chunk,
+            chunk_size,
+            chunk_count,
+            total_bytes,
+            _algorithm: core::marker::PhantomData,
+        })
+    }
+}
+
+impl<D: DigestAlgorithm + Send + Sync> Context<D> {
+    /// Hash a full in-memory buffer in parallel across available cores,
+    /// producing the same [`Digest`] as feeding `data` through
+    /// [`Context::consume`]/[`Context::finalize`] sequentially with the same
+    /// `chunk_size`.
+    ///
+    /// Each `chunk_size` block is hashed independently via rayon, the
+    /// resulting hashes are concatenated back in their original order, and
+    /// the concatenation is hashed once more to produce the final digest,
+    /// exactly mirroring the sequential algorithm.
+    pub fn finalize_parallel(data: &[u8], chunk_size: usize) -> Digest {
+        let chunks: Vec<&[u8]> = data.chunks(chunk_size).collect();
+
+        let output_size = <D as DigestAlgorithm>::output_size();
+        let mut combined_hashes = Vec::with_capacity(chunks.len() * output_size);
+        let hashes: Vec<_> = chunks
+            .par_iter()
+            .map(|chunk| {
+                let mut hasher = D::new();
+                hasher.update(chunk);
+                hasher.finalize()
+            })
+            .collect();
+        for hash in &hashes {
+            combined_hashes.extend_from_slice(hash);
+        }
+
+        let mut hasher = D::new();
+        hasher.update(&combined_hashes);
+        let final_hash = hasher.finalize();
+        Digest {
+            hash: final_hash.to_vec(),
+            parts: chunks.len(),
+        }
+    }
 }
 
-impl Default for Context {
+impl<D: DigestAlgorithm> Default for Context<D> {
     #[inline]
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl core::convert::From<Context> for Digest {
+impl<D: DigestAlgorithm> core::convert::From<Context<D>> for Digest {
     #[inline]
-    fn from(ctx: Context) -> Self {
+    fn from(ctx: Context<D>) -> Self {
         ctx.finalize()
     }
 }
 
-impl std::io::Write for Context {
+impl<D: DigestAlgorithm> std::io::Write for Context<D> {
     #[inline]
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         self.consume(buf);
@@ -177,10 +362,40 @@ impl std::io::Write for Context {
     }
 }
 
+impl<D: DigestAlgorithm> digest::OutputSizeUser for Context<D> {
+    type OutputSize = D::OutputSize;
+}
+
+impl<D: DigestAlgorithm> digest::HashMarker for Context<D> {}
+
+impl<D: DigestAlgorithm> digest::Update for Context<D> {
+    #[inline]
+    fn update(&mut self, data: &[u8]) {
+        self.consume(data);
+    }
+}
+
+impl<D: DigestAlgorithm> digest::FixedOutput for Context<D> {
+    #[inline]
+    fn finalize_into(self, out: &mut digest::Output<Self>) {
+        out.copy_from_slice(self.finalize().hash());
+    }
+}
+
+impl<D: DigestAlgorithm> digest::Reset for Context<D> {
+    #[inline]
+    fn reset(&mut self) {
+        self.combined_hashes.clear();
+        self.current_chunk.clear();
+        self.chunk_count = 0;
+        self.total_bytes = 0;
+    }
+}
+
 /// Compute the digest of data with default chunk size (8 MiB).
 #[inline]
 pub fn compute<T: AsRef<[u8]>>(data: T) -> Digest {
-    let mut ctx = Context::new();
+    let mut ctx: Context = Context::new();
     ctx.consume(data);
     ctx.finalize()
 }
@@ -188,14 +403,75 @@ pub fn compute<T: AsRef<[u8]>>(data: T) -> Digest {
 /// Compute the digest of data with specified chunk size in bytes.
 #[inline]
 pub fn compute_with_chunk_size<T: AsRef<[u8]>>(data: T, chunk_size: usize) -> Digest {
-    let mut ctx = Context::with_chunk_size(chunk_size);
+    let mut ctx: Context = Context::with_chunk_size(chunk_size);
     ctx.consume(data);
     ctx.finalize()
 }
 
+/// Compute the digest of data with default chunk size (8 MiB), hashing
+/// chunks in parallel across available cores. Produces the same result as
+/// [`compute`].
+#[inline]
+pub fn compute_parallel<T: AsRef<[u8]>>(data: T) -> Digest {
+    compute_parallel_with_chunk_size(data, 8 * 1024 * 1024)
+}
+
+/// Compute the digest of data with specified chunk size in bytes, hashing
+/// chunks in parallel across available cores. Produces the same result as
+/// [`compute_with_chunk_size`].
+#[inline]
+pub fn compute_parallel_with_chunk_size<T: AsRef<[u8]>>(data: T, chunk_size: usize) -> Digest {
+    Context::<Md5>::finalize_parallel(data.as_ref(), chunk_size)
+}
+
+const MIB: u64 = 1024 * 1024;
+
+/// Recover the part size used to produce a multipart ETag when it is
+/// unknown, given the full file contents and the expected ETag in
+/// `<hex>-<N>` form.
+///
+/// For a file of length `L` split into `N` parts, the part size `S` must
+/// satisfy `ceil(L / S) == N`, i.e. `(N - 1) * S < L <= N * S`, which bounds
+/// `S` to the inclusive range `[ceil(L/N), floor((L-1)/(N-1))]`. A single
+/// part (`N == 1`) has no upper bound — any `S >= L` produces the same
+/// digest, since the whole file fits in one chunk regardless of `S` — so
+/// only the smallest such whole-MiB size is tried. Otherwise candidates are
+/// restricted to whole-MiB sizes within that range, which keeps the search
+/// small, and each is checked with [`compute_with_chunk_size`] until one
+/// reproduces `expected_etag`.
+pub fn recover_chunk_size<T: AsRef<[u8]>>(data: T, expected_etag: &str) -> Option<usize> {
+    let data = data.as_ref();
+    let expected_etag = expected_etag.trim_matches('"');
+    let (_, parts) = expected_etag.rsplit_once('-')?;
+    let parts: u64 = parts.parse().ok()?;
+    if parts == 0 {
+        return None;
+    }
+
+    let total_size = data.len() as u64;
+    let (first_mib, last_mib) = if parts == 1 {
+        let mib = total_size.div_ceil(MIB).max(1);
+        (mib, mib)
+    } else {
+        let lower = total_size.div_ceil(parts);
+        let upper = total_size.checked_sub(1)? / (parts - 1);
+        (lower.div_ceil(MIB).max(1), upper / MIB)
+    };
+
+    for mib in first_mib..=last_mib {
+        let candidate = mib * MIB;
+        let digest = compute_with_chunk_size(data, candidate as usize);
+        if format!("{digest:x}") == expected_etag {
+            return Some(candidate as usize);
+        }
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::Context;
+    use sha2::Sha256;
 
     #[test]
     fn compute() {
@@ -210,7 +486,7 @@ mod tests {
             let digest = super::compute(input);
             assert_eq!(format!("{digest:x}"), output);
 
-            let mut context = Context::new();
+            let mut context: Context = Context::new();
             context.consume(input);
             let digest = context.finalize();
             assert_eq!(format!("{digest:x}"), output);
@@ -222,10 +498,148 @@ mod tests {
             let digest = super::compute_with_chunk_size(input, *chunk_size * 1024 * 1024);
             assert_eq!(format!("{digest:x}"), output);
 
-            let mut context = Context::with_chunk_size(*chunk_size * 1024 * 1024);
+            let mut context: Context = Context::with_chunk_size(*chunk_size * 1024 * 1024);
             context.consume(input);
             let digest = context.finalize();
             assert_eq!(format!("{digest:x}"), output);
         }
     }
+
+    #[test]
+    fn compute_parallel_matches_sequential() {
+        let large = "a".repeat(8 * 1024 * 1024 + 1);
+        for input in ["", "hello", "hello\n", &large] {
+            assert_eq!(
+                format!("{:x}", super::compute(input)),
+                format!("{:x}", super::compute_parallel(input)),
+            );
+        }
+
+        for chunk_size in [2usize, 3] {
+            let digest = super::compute_with_chunk_size(&large, chunk_size * 1024 * 1024);
+            let digest_parallel =
+                super::compute_parallel_with_chunk_size(&large, chunk_size * 1024 * 1024);
+            assert_eq!(format!("{digest:x}"), format!("{digest_parallel:x}"));
+        }
+    }
+
+    #[test]
+    fn checkpoint_and_resume() {
+        let large = "a".repeat(8 * 1024 * 1024 + 1);
+        let (first_half, second_half) = large.split_at(3 * 1024 * 1024 + 1);
+
+        let expected = super::compute(&large);
+
+        let mut context: Context = Context::new();
+        context.consume(first_half);
+        let bytes = context.into_bytes();
+
+        let mut resumed: Context = Context::from_bytes(&bytes).unwrap();
+        resumed.consume(second_half);
+        let digest = resumed.finalize();
+
+        assert_eq!(format!("{digest:x}"), format!("{expected:x}"));
+    }
+
+    #[test]
+    fn from_bytes_rejects_truncated_and_unsupported_input() {
+        assert!(matches!(
+            Context::<super::Md5>::from_bytes(&[]),
+            Err(super::FromBytesError::Truncated)
+        ));
+        assert!(matches!(
+            Context::<super::Md5>::from_bytes(&[7]),
+            Err(super::FromBytesError::UnsupportedVersion(7))
+        ));
+    }
+
+    #[test]
+    fn from_bytes_rejects_invalid_state() {
+        // chunk_size == 0 would make consume's space_left always 0, hanging
+        // forever since remaining never shrinks.
+        let mut zero_chunk_size = Context::<super::Md5>::with_chunk_size(4).into_bytes();
+        zero_chunk_size[1..9].copy_from_slice(&0u64.to_le_bytes());
+        assert!(matches!(
+            Context::<super::Md5>::from_bytes(&zero_chunk_size),
+            Err(super::FromBytesError::InvalidState)
+        ));
+
+        // current_chunk.len() > chunk_size would make chunk_size -
+        // current_chunk.len() underflow and panic on the next consume.
+        let mut context: Context = Context::with_chunk_size(8);
+        context.consume("abc");
+        let mut overfull = context.into_bytes();
+        overfull[1..9].copy_from_slice(&2u64.to_le_bytes());
+        assert!(matches!(
+            Context::<super::Md5>::from_bytes(&overfull),
+            Err(super::FromBytesError::InvalidState)
+        ));
+    }
+
+    #[test]
+    fn implements_digest_trait_bound() {
+        fn requires_digest<T: digest::Digest>() {}
+        requires_digest::<Context>();
+    }
+
+    #[test]
+    fn generic_over_digest_algorithm() {
+        let mut context = Context::<Sha256>::new();
+        context.consume("hello");
+        let digest = context.finalize();
+        assert_eq!(digest.hash().len(), 32);
+        assert_eq!(digest.parts(), 1);
+    }
+
+    #[test]
+    fn implements_digest_traits() {
+        use digest::{FixedOutput, Reset, Update};
+
+        let mut context = Context::<super::Md5>::new();
+        context.update(b"hello");
+        assert_eq!(context.parts(), 1);
+
+        let expected = super::compute("hello");
+        let via_trait = context.clone().finalize_fixed();
+        assert_eq!(via_trait.as_slice(), expected.hash());
+
+        context.reset();
+        assert_eq!(context.parts(), 0);
+        assert_eq!(context.total_bytes(), 0);
+    }
+
+    #[test]
+    fn recover_chunk_size() {
+        let large = "a".repeat(8 * 1024 * 1024 + 1);
+        let etag = "2b26d4c146cf1500e532eed66eba4a36-5";
+
+        let chunk_size = super::recover_chunk_size(&large, etag).unwrap();
+        assert_eq!(chunk_size, 2 * 1024 * 1024);
+
+        assert_eq!(super::recover_chunk_size(&large, "deadbeef-999"), None);
+    }
+
+    #[test]
+    fn recover_chunk_size_single_part() {
+        // Most real S3 objects are under the multipart threshold and upload
+        // as a single part, so this is the common case.
+        let data = "a".repeat(123);
+        let etag = format!("{:x}", super::compute(&data));
+        assert!(etag.ends_with("-1"));
+
+        let chunk_size = super::recover_chunk_size(&data, &etag).unwrap();
+        assert!(chunk_size as u64 >= data.len() as u64);
+    }
+
+    #[test]
+    fn recover_chunk_size_exact_multiple() {
+        // The file length is an exact multiple of chunk_size * parts, so
+        // the correct chunk size sits right on the lower search boundary.
+        let data = "a".repeat(5 * 1024 * 1024);
+        let etag = format!("{:x}", super::compute_with_chunk_size(&data, 1024 * 1024));
+        assert!(etag.ends_with("-5"));
+
+        let chunk_size = super::recover_chunk_size(&data, &etag).unwrap();
+        assert_eq!(chunk_size, 1024 * 1024);
+    }
 }